@@ -1,10 +1,12 @@
 use std::fmt;
-use std::sync::mpsc::channel;
+use std::sync::Arc;
 use std::time::Instant;
 
 use clap::Parser;
-use rand::{seq::SliceRandom, Rng};
-use threadpool::ThreadPool;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
 
 #[derive(Parser, Clone)]
 struct Args {
@@ -22,6 +24,36 @@ struct Args {
 
     #[clap(short, long, value_parser, default_value_t = 1_000_000)]
     iterations: usize,
+
+    /// Number of threads in the rayon pool used to run the simulation.
+    #[clap(short, long, value_parser, default_value_t = 16)]
+    threads: usize,
+
+    /// Skip simulation entirely and compute the exact success probability for
+    /// `prisoners` and `chances` via the cycle-length recurrence.
+    #[clap(short, long, action, default_value_t = false)]
+    analytic: bool,
+
+    /// Seed the RNG for reproducible runs. With no seed supplied, one is drawn
+    /// from entropy and printed so the run can be replayed later.
+    #[clap(short, long, value_parser)]
+    seed: Option<u64>,
+
+    /// Instead of simulating wins, decompose each layout into cycles and
+    /// report a histogram of the longest-cycle length across all iterations.
+    #[clap(long, action, default_value_t = false)]
+    analyze: bool,
+
+    /// Number of longest cycle lengths to report from the single worst-case
+    /// layout seen during --analyze.
+    #[clap(long, value_parser, default_value_t = 10)]
+    top: usize,
+
+    /// Run every registered strategy over the same seeded sequence of
+    /// layouts and print a side-by-side table of win rates and wall-clock
+    /// times, instead of running a single strategy.
+    #[clap(long, action, default_value_t = false)]
+    compare: bool,
 }
 
 impl fmt::Display for Args {
@@ -46,28 +78,37 @@ struct Setup {
     pub count: usize,
     pub chances: usize,
 
-    rng: rand::rngs::ThreadRng,
+    rng: ChaCha8Rng,
 }
 
 impl Setup {
-    fn new(args: &Args) -> Setup {
+    /// Builds a `Setup` whose box-shuffling RNG is deterministically derived
+    /// from `seed`, so the same seed always produces the same sequence of
+    /// layouts.
+    fn new(args: &Args, seed: u64) -> Setup {
         // There are `count` numbered slips and `count` numbered boxes, one for each
-        // prisoner, and each slip is randomly placed in a box.
-        let slips_seen: Vec<bool> = match args.optimized {
-            true => vec![false; args.prisoners],
-            false => vec![],
-        };
-
+        // prisoner, and each slip is randomly placed in a box. `slips_seen` is
+        // always allocated, even though only `run_optimized` uses it, so that
+        // any `Strategy` can be run against the same `Setup` -- as `--compare`
+        // does.
         Setup {
             boxes: (0..args.prisoners).collect(),
-            slips_seen,
+            slips_seen: vec![false; args.prisoners],
             count: args.prisoners,
             chances: args.chances,
-            rng: rand::thread_rng(),
+            rng: ChaCha8Rng::seed_from_u64(seed),
         }
     }
 
     fn reset(&mut self) {
+        // Restore the identity layout before shuffling -- `shuffle` permutes
+        // whatever order `boxes` is currently in, so shuffling on top of a
+        // leftover permutation from a prior iteration would make the result
+        // depend on that history, not just on `rng`'s state.
+        for (slip, b) in self.boxes.iter_mut().enumerate() {
+            *b = slip;
+        }
+
         self.boxes.shuffle(&mut self.rng);
         self.slips_seen.fill(false);
     }
@@ -185,8 +226,6 @@ fn run_optimized(setup: &mut Setup) -> bool {
 /// The below function is the naive approach to the problem. Each of the prisoners picks
 /// a random box to open. They have 50 attempts to pick the box with their number in it.
 fn run_naive(setup: &mut Setup) -> bool {
-    let mut rng = rand::thread_rng();
-
     let mut prisoners: Vec<bool> = vec![false; setup.count];
     let mut opened_boxes: Vec<bool> = prisoners.clone();
 
@@ -195,7 +234,7 @@ fn run_naive(setup: &mut Setup) -> bool {
             let mut to_open: usize;
 
             loop {
-                to_open = rng.gen_range(0..setup.count);
+                to_open = setup.rng.gen_range(0..setup.count);
 
                 if !opened_boxes[to_open] {
                     opened_boxes[to_open] = true;
@@ -217,12 +256,10 @@ fn run_naive(setup: &mut Setup) -> bool {
 
 /// The below function is an optimized version of the naive logic.
 fn run_naive_optimized(setup: &mut Setup) -> bool {
-    let mut rng = rand::thread_rng();
-
     let mut to_open: Vec<usize> = setup.boxes.clone();
 
     for prisoner in 0..setup.count {
-        to_open.shuffle(&mut rng);
+        to_open.shuffle(&mut setup.rng);
 
         for idx in 0..=setup.chances {
             if idx == setup.chances {
@@ -239,54 +276,414 @@ fn run_naive_optimized(setup: &mut Setup) -> bool {
     true
 }
 
-fn main() {
-    let threads: usize = 16;
-    let pool = ThreadPool::new(16);
-    let (tx, rx) = channel();
+/// A single box-opening algorithm. Contributing a new strategy is one
+/// `impl Strategy` plus an entry in `strategies()` (so `--compare` runs it)
+/// and, to make it selectable by `--version`/`--optimized`, in
+/// `strategy_for`.
+trait Strategy {
+    fn name(&self) -> &str;
+    fn attempt(&self, setup: &mut Setup) -> bool;
+}
 
-    let args = Args::parse();
+struct Solved;
 
-    let handler = match (&args.version[..], args.optimized) {
-        ("naive", false) => run_naive,
-        ("naive", true) => run_naive_optimized,
-        (_, false) => run,
-        (_, true) => run_optimized,
-    };
+impl Strategy for Solved {
+    fn name(&self) -> &str {
+        "solved"
+    }
 
-    let start = Instant::now();
+    fn attempt(&self, setup: &mut Setup) -> bool {
+        run(setup)
+    }
+}
+
+struct SolvedOptimized;
+
+impl Strategy for SolvedOptimized {
+    fn name(&self) -> &str {
+        "solved-optimized"
+    }
+
+    fn attempt(&self, setup: &mut Setup) -> bool {
+        run_optimized(setup)
+    }
+}
+
+struct Naive;
+
+impl Strategy for Naive {
+    fn name(&self) -> &str {
+        "naive"
+    }
 
-    for i in 0..threads {
-        let tx = tx.clone();
-        let args = args.clone();
-        let to_execute = match i + 1 == threads {
-            true => (args.iterations / threads) + (args.iterations % threads),
-            false => args.iterations / threads,
+    fn attempt(&self, setup: &mut Setup) -> bool {
+        run_naive(setup)
+    }
+}
+
+struct NaiveOptimized;
+
+impl Strategy for NaiveOptimized {
+    fn name(&self) -> &str {
+        "naive-optimized"
+    }
+
+    fn attempt(&self, setup: &mut Setup) -> bool {
+        run_naive_optimized(setup)
+    }
+}
+
+/// Every registered strategy, in the order `--compare` reports them.
+fn strategies() -> Vec<Box<dyn Strategy>> {
+    vec![
+        Box::new(Solved),
+        Box::new(SolvedOptimized),
+        Box::new(Naive),
+        Box::new(NaiveOptimized),
+    ]
+}
+
+/// Picks the strategy named by `args.version`/`args.optimized`, matching the
+/// CLI's existing `--version`/--optimized` flags.
+fn strategy_for(args: &Args) -> Arc<dyn Strategy + Send + Sync> {
+    match (&args.version[..], args.optimized) {
+        ("naive", false) => Arc::new(Naive),
+        ("naive", true) => Arc::new(NaiveOptimized),
+        (_, false) => Arc::new(Solved),
+        (_, true) => Arc::new(SolvedOptimized),
+    }
+}
+
+/// Runs `--compare` mode: every registered strategy plays the same seeded
+/// sequence of `args.iterations` layouts, so win rates and timings are
+/// directly comparable.
+fn run_compare(args: &Args, seed: u64) {
+    println!(
+        "comparing {} strategies over {} iterations (seed {}):",
+        strategies().len(),
+        args.iterations,
+        seed,
+    );
+    println!("{:<18} {:>10} {:>9} {:>10}", "strategy", "wins", "rate", "elapsed");
+
+    for strategy in strategies() {
+        let mut setup = Setup::new(args, seed);
+        let mut wins: u32 = 0;
+        let start = Instant::now();
+
+        for i in 0..args.iterations {
+            // Re-seed per iteration, as `main` does: the naive strategies draw
+            // from `setup.rng` themselves, so a single shared stream would give
+            // each strategy a different sequence of layouts.
+            setup.rng = ChaCha8Rng::seed_from_u64(seed.wrapping_add(i as u64));
+            setup.reset();
+
+            wins += strategy.attempt(&mut setup) as u32;
+        }
+
+        let elapsed = start.elapsed();
+
+        println!(
+            "{:<18} {:>10} {:>8.2}% {:>9.3}s",
+            strategy.name(),
+            wins,
+            (wins as f64 / args.iterations as f64) * 100.0,
+            elapsed.as_secs_f64(),
+        );
+    }
+}
+
+/// Decomposes a permutation into its disjoint cycles and returns the length
+/// of each one. The success condition of the whole exercise is exactly
+/// "every cycle length is at most `chances`", so these lengths fully explain
+/// the win rate.
+fn cycle_lengths(boxes: &[usize]) -> Vec<usize> {
+    let mut visited = vec![false; boxes.len()];
+    let mut lengths = Vec::new();
+
+    for start in 0..boxes.len() {
+        if visited[start] {
+            continue;
+        }
+
+        let mut length = 0;
+        let mut next = start;
+
+        while !visited[next] {
+            visited[next] = true;
+            next = boxes[next];
+            length += 1;
+        }
+
+        lengths.push(length);
+    }
+
+    lengths
+}
+
+/// Returns the `n` largest cycle lengths, in descending order. Uses
+/// `select_nth_unstable` to partition out the top `n` in O(len) average time
+/// rather than sorting the whole slice.
+fn top_cycle_lengths(lengths: &mut [usize], n: usize) -> &[usize] {
+    let n = n.min(lengths.len());
+
+    if n == 0 {
+        return &[];
+    }
+
+    let split = lengths.len() - n;
+    lengths.select_nth_unstable(split);
+
+    let top = &mut lengths[split..];
+    top.sort_unstable_by(|a, b| b.cmp(a));
+    top
+}
+
+/// Runs `--analyze` mode: aggregates a histogram of longest-cycle lengths
+/// across `args.iterations` random layouts, reports the empirical
+/// P(longest cycle > chances), and prints the top cycle lengths seen in the
+/// single worst-case layout encountered.
+fn run_analyze(args: &Args, seed: u64) {
+    let mut setup = Setup::new(args, seed);
+    let mut histogram = vec![0usize; args.prisoners + 1];
+    let mut over_chances = 0usize;
+    let mut worst_case: Vec<usize> = Vec::new();
+    let mut worst_longest = 0usize;
+
+    for _ in 0..args.iterations {
+        setup.reset();
+
+        let lengths = cycle_lengths(&setup.boxes);
+        let longest = lengths.iter().copied().max().unwrap_or(0);
+
+        histogram[longest] += 1;
+
+        if longest > args.chances {
+            over_chances += 1;
+        }
+
+        if longest > worst_longest {
+            worst_longest = longest;
+            worst_case = lengths;
+        }
+    }
+
+    let bucket_width = (args.prisoners / 20).max(1);
+
+    println!("longest-cycle histogram ({} iterations):", args.iterations);
+
+    for bucket_start in (0..=args.prisoners).step_by(bucket_width) {
+        let bucket_end = (bucket_start + bucket_width - 1).min(args.prisoners);
+        let count: usize = histogram[bucket_start..=bucket_end].iter().sum();
+        let bar = "#".repeat((count * 50 / args.iterations.max(1)).max(usize::from(count > 0)));
+
+        println!("  {:>4}-{:<4} {:>8} {}", bucket_start, bucket_end, count, bar);
+    }
+
+    println!(
+        "P(longest cycle > {} chances) = {:.4}",
+        args.chances,
+        over_chances as f64 / args.iterations as f64,
+    );
+
+    let top = top_cycle_lengths(&mut worst_case, args.top);
+
+    println!(
+        "top {} cycle lengths from the worst-case layout seen (longest = {}): {:?}",
+        top.len(),
+        worst_longest,
+        top,
+    );
+}
+
+/// Aggregates the win counts reported for each batch into a success rate with
+/// a Wald 95% confidence interval, plus (when more than one batch is
+/// reported) the standard deviation of the per-batch rate. In `main` each
+/// batch is one rayon split's own win count and iteration count, and each is
+/// an independent sample of the overall rate.
+struct Stats {
+    iterations: usize,
+    wins: u32,
+    rate: f64,
+    ci95: (f64, f64),
+    batch_stddev: Option<f64>,
+}
+
+impl Stats {
+    fn from_batches(batches: &[(u32, usize)]) -> Stats {
+        let iterations: usize = batches.iter().map(|(_, count)| count).sum();
+        let wins: u32 = batches.iter().map(|(wins, _)| wins).sum();
+        let rate = wins as f64 / iterations as f64;
+        let se = (rate * (1.0 - rate) / iterations as f64).sqrt();
+        let ci95 = (rate - 1.96 * se, rate + 1.96 * se);
+
+        let batch_stddev = if batches.len() > 1 {
+            let rates: Vec<f64> = batches
+                .iter()
+                .map(|(wins, count)| *wins as f64 / *count as f64)
+                .collect();
+            let mean = rates.iter().sum::<f64>() / rates.len() as f64;
+            let variance = rates.iter().map(|rate| (rate - mean).powi(2)).sum::<f64>()
+                / (rates.len() - 1) as f64;
+
+            Some(variance.sqrt())
+        } else {
+            None
         };
 
-        pool.execute(move || {
-            let mut wins: u32 = 0;
-            let mut setup: Setup = Setup::new(&args);
+        Stats {
+            iterations,
+            wins,
+            rate,
+            ci95,
+            batch_stddev,
+        }
+    }
+}
 
-            for _ in 0..to_execute {
-                setup.reset();
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "of {} runs, {} were successful ({:.2}% \u{b1} {:.2}%, 95% CI [{:.2}%, {:.2}%])",
+            self.iterations,
+            self.wins,
+            self.rate * 100.0,
+            (self.ci95.1 - self.rate) * 100.0,
+            self.ci95.0 * 100.0,
+            self.ci95.1 * 100.0,
+        )?;
+
+        if let Some(stddev) = self.batch_stddev {
+            write!(f, ", between-batch stddev {:.2}%", stddev * 100.0)?;
+        }
 
-                wins += handler(&mut setup) as u32;
-            }
+        Ok(())
+    }
+}
 
-            tx.send(wins).unwrap();
-        });
+/// Computes the exact probability that a uniformly random permutation of `n`
+/// elements has no cycle longer than `k`, i.e. the ground-truth win rate for
+/// `n` prisoners given `k` chances.
+///
+/// Let f(m) be the probability that a random permutation of `m` elements has
+/// every cycle of length at most `k`, with f(0) = 1. Fixing any one element,
+/// the cycle containing it has length `j` (1 <= j <= min(m, k)) with
+/// probability 1/m each, and the remaining `m - j` elements must independently
+/// satisfy the same property, giving the recurrence:
+///
+///     f(m) = (1/m) * sum_{j=1}^{min(m,k)} f(m - j)
+///
+/// The answer is f(n), computed bottom-up in O(n*k).
+fn analytic_probability(n: usize, k: usize) -> f64 {
+    if k >= n {
+        return 1.0;
+    }
+
+    if k == 0 {
+        return 0.0;
     }
 
-    let wins: u32 = rx.iter().take(threads as usize).fold(0, |a, b| a + b);
+    let mut f = vec![0.0_f64; n + 1];
+    f[0] = 1.0;
+
+    for m in 1..=n {
+        let mut sum = 0.0;
+
+        for j in 1..=k.min(m) {
+            sum += f[m - j];
+        }
+
+        f[m] = sum / m as f64;
+    }
+
+    f[n]
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if args.analytic {
+        println!(
+            "exact probability for {} prisoners with {} chances: {:.5}",
+            args.prisoners,
+            args.chances,
+            analytic_probability(args.prisoners, args.chances),
+        );
+
+        return;
+    }
+
+    let seed = args.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    println!("using seed: {}", seed);
+
+    if args.analyze {
+        run_analyze(&args, seed);
+
+        return;
+    }
+
+    if args.compare {
+        run_compare(&args, seed);
+
+        return;
+    }
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(args.threads)
+        .build()
+        .expect("failed to build rayon thread pool");
+
+    let strategy = strategy_for(&args);
+
+    let start = Instant::now();
+
+    // Each rayon split reuses one `Setup` across the iterations it processes,
+    // so allocation is amortized the same way a manual thread-local would be
+    // -- but its RNG is re-seeded from `seed.wrapping_add(i as u64)` on every
+    // single iteration, not just the split's first one. That keeps the
+    // layout at a given index a pure function of (seed, index), regardless
+    // of where rayon's work-stealing happens to draw split boundaries --
+    // those boundaries shift with `--threads`/core count, so deriving a
+    // layout from "how far into this chunk's RNG stream we are" would make
+    // reproducibility depend on an undocumented, machine-specific thread
+    // count instead of just `--seed`. `with_min_len` keeps splits close to
+    // one thread's fair share so the per-batch rates that feed
+    // `Stats::from_batches` stay meaningful -- rayon's default recursive
+    // splitting would otherwise hand back a long tail of tiny, high-variance
+    // batches. It's sized off the pool's actual thread count, since
+    // `--threads 0` asks rayon to fall back to its own default (all cores).
+    let min_batch_len = (args.iterations / pool.current_num_threads().max(1)).max(1);
+
+    let batches: Vec<(u32, usize)> = pool.install(|| {
+        (0..args.iterations)
+            .into_par_iter()
+            .with_min_len(min_batch_len)
+            .fold(
+                || (0u32, 0usize, None::<Setup>),
+                |(wins, count, setup), i| {
+                    let mut setup = setup.unwrap_or_else(|| Setup::new(&args, seed));
+
+                    setup.rng = ChaCha8Rng::seed_from_u64(seed.wrapping_add(i as u64));
+                    setup.reset();
+
+                    let win = strategy.attempt(&mut setup) as u32;
+
+                    (wins + win, count + 1, Some(setup))
+                },
+            )
+            .map(|(wins, count, _setup)| (wins, count))
+            .collect()
+    });
+
+    let stats = Stats::from_batches(&batches);
 
     let finished = start.elapsed();
 
     println!(
-        "complete in {:.3} seconds! of {} runs, {} were successful ({:.2}%)",
+        "complete in {:.3} seconds! {}",
         finished.as_millis() as f32 / 1000 as f32,
-        args.iterations,
-        wins,
-        (wins as f32 / args.iterations as f32) * 100.0,
+        stats,
     );
 }
 
@@ -303,7 +700,7 @@ mod tests {
             slips_seen: vec![],
             count: 10,
             chances: 5,
-            rng: rand::thread_rng(),
+            rng: ChaCha8Rng::seed_from_u64(0),
         };
 
         assert!(run(&mut setup));
@@ -317,7 +714,7 @@ mod tests {
             slips_seen: vec![],
             count: 10,
             chances: 5,
-            rng: rand::thread_rng(),
+            rng: ChaCha8Rng::seed_from_u64(0),
         };
 
         assert_eq!(run(&mut setup), false);
@@ -331,7 +728,7 @@ mod tests {
             slips_seen: vec![false; 10],
             count: 10,
             chances: 5,
-            rng: rand::thread_rng(),
+            rng: ChaCha8Rng::seed_from_u64(0),
         };
 
         assert!(run_optimized(&mut setup));
@@ -345,7 +742,7 @@ mod tests {
             slips_seen: vec![false; 10],
             count: 10,
             chances: 5,
-            rng: rand::thread_rng(),
+            rng: ChaCha8Rng::seed_from_u64(0),
         };
 
         assert_eq!(run_optimized(&mut setup), false);
@@ -360,7 +757,7 @@ mod tests {
             slips_seen: vec![],
             count: 10,
             chances: 10,
-            rng: rand::thread_rng(),
+            rng: ChaCha8Rng::seed_from_u64(0),
         };
 
         assert!(run_naive(&mut setup));
@@ -375,9 +772,180 @@ mod tests {
             slips_seen: vec![],
             count: 10,
             chances: 10,
-            rng: rand::thread_rng(),
+            rng: ChaCha8Rng::seed_from_u64(0),
         };
 
         assert!(run_naive_optimized(&mut setup));
     }
+
+    #[test]
+    fn test_stats_from_batches_aggregates_rate_and_ci() {
+        let stats = Stats::from_batches(&[(50, 100), (50, 100)]);
+
+        assert_eq!(stats.iterations, 200);
+        assert_eq!(stats.wins, 100);
+        assert!((stats.rate - 0.5).abs() < f64::EPSILON);
+        assert!(stats.ci95.0 < stats.rate && stats.rate < stats.ci95.1);
+        assert!(stats.batch_stddev.unwrap().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_stats_from_batches_single_batch_has_no_stddev() {
+        let stats = Stats::from_batches(&[(31, 100)]);
+
+        assert!(stats.batch_stddev.is_none());
+    }
+
+    #[test]
+    fn test_cycle_lengths_known_layout() {
+        // Use the box layout from the documentation above: three cycles of
+        // length 5, 4, and 1.
+        let boxes = vec![4, 3, 9, 2, 7, 8, 6, 5, 0, 1];
+        let mut lengths = cycle_lengths(&boxes);
+
+        lengths.sort_unstable();
+
+        assert_eq!(lengths, vec![1, 4, 5]);
+    }
+
+    #[test]
+    fn test_top_cycle_lengths_returns_largest_descending() {
+        let mut lengths = vec![3, 1, 4, 1, 5, 9, 2, 6];
+
+        assert_eq!(top_cycle_lengths(&mut lengths, 3), &[9, 6, 5]);
+    }
+
+    #[test]
+    fn test_top_cycle_lengths_caps_at_slice_len() {
+        let mut lengths = vec![2, 1];
+
+        assert_eq!(top_cycle_lengths(&mut lengths, 10), &[2, 1]);
+    }
+
+    #[test]
+    fn test_strategy_for_matches_cli_flags() {
+        let base = Args {
+            version: String::from("solved"),
+            optimized: false,
+            prisoners: 10,
+            chances: 5,
+            iterations: 1,
+            threads: 1,
+            analytic: false,
+            seed: None,
+            analyze: false,
+            top: 10,
+            compare: false,
+        };
+
+        assert_eq!(strategy_for(&base).name(), "solved");
+        assert_eq!(
+            strategy_for(&Args { optimized: true, ..base.clone() }).name(),
+            "solved-optimized",
+        );
+        assert_eq!(
+            strategy_for(&Args { version: String::from("naive"), ..base.clone() }).name(),
+            "naive",
+        );
+        assert_eq!(
+            strategy_for(&Args { version: String::from("naive"), optimized: true, ..base }).name(),
+            "naive-optimized",
+        );
+    }
+
+    #[test]
+    fn test_analytic_probability_chances_covers_all() {
+        assert_eq!(analytic_probability(100, 100), 1.0);
+        assert_eq!(analytic_probability(100, 150), 1.0);
+    }
+
+    #[test]
+    fn test_analytic_probability_zero_chances() {
+        assert_eq!(analytic_probability(100, 0), 0.0);
+    }
+
+    #[test]
+    fn test_analytic_probability_matches_known_value() {
+        // The classic 100 prisoners / 50 boxes case converges to ~31.18%.
+        let p = analytic_probability(100, 50);
+
+        assert!((p - 0.31183).abs() < 0.0001);
+    }
+}
+
+/// Property-based tests asserting that `run` and `run_optimized` always agree
+/// with each other and with the "longest cycle <= chances" invariant that the
+/// whole exercise boils down to. This is exactly the kind of check that would
+/// have caught an off-by-one in `run_optimized`'s `idx == setup.chances`
+/// early-exit.
+#[cfg(test)]
+mod proptest_invariants {
+    use super::{cycle_lengths, run, run_naive_optimized, run_optimized, ChaCha8Rng, SeedableRng, Setup};
+    use proptest::prelude::*;
+
+    /// Generates an arbitrary permutation of `0..n` via a Fisher-Yates
+    /// shuffle driven by proptest-generated swap indices. Every permutation
+    /// can be produced, but since each draw in `0..n` is reduced modulo the
+    /// remaining length, they are not all equally likely.
+    fn permutation(n: usize) -> impl Strategy<Value = Vec<usize>> {
+        prop::collection::vec(0..n.max(1), n).prop_map(move |draws| {
+            let mut perm: Vec<usize> = (0..n).collect();
+
+            for (i, draw) in draws.iter().enumerate() {
+                let remaining = n - i;
+                let j = i + draw % remaining;
+                perm.swap(i, j);
+            }
+
+            perm
+        })
+    }
+
+    /// Generates an arbitrary permutation together with an arbitrary
+    /// `chances` value in `0..=count`.
+    fn layout_and_chances() -> impl Strategy<Value = (Vec<usize>, usize)> {
+        (1usize..=40).prop_flat_map(|count| (permutation(count), 0..=count))
+    }
+
+    fn setup_for(boxes: Vec<usize>, chances: usize) -> Setup {
+        let count = boxes.len();
+
+        Setup {
+            slips_seen: vec![false; count],
+            boxes,
+            count,
+            chances,
+            rng: ChaCha8Rng::seed_from_u64(0),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn run_and_run_optimized_agree_with_cycle_invariant((boxes, chances) in layout_and_chances()) {
+            let longest = cycle_lengths(&boxes).into_iter().max().unwrap_or(0);
+            let expected = longest <= chances;
+
+            let mut plain_setup = setup_for(boxes.clone(), chances);
+            let mut optimized_setup = setup_for(boxes, chances);
+
+            prop_assert_eq!(run(&mut plain_setup), run_optimized(&mut optimized_setup));
+            prop_assert_eq!(run(&mut plain_setup), expected);
+        }
+
+        #[test]
+        fn run_always_wins_with_full_chances(boxes in (1usize..=40).prop_flat_map(permutation)) {
+            let count = boxes.len();
+            let mut setup = setup_for(boxes, count);
+
+            prop_assert!(run(&mut setup));
+        }
+
+        #[test]
+        fn run_naive_optimized_always_wins_with_full_chances(boxes in (1usize..=40).prop_flat_map(permutation)) {
+            let count = boxes.len();
+            let mut setup = setup_for(boxes, count);
+
+            prop_assert!(run_naive_optimized(&mut setup));
+        }
+    }
 }